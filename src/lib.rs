@@ -28,7 +28,10 @@
 #![forbid(unsafe_code)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
-use csv::StringRecord;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use csv::ByteRecord;
 use parse::CsvRow;
 use serde::de::DeserializeOwned;
 
@@ -40,14 +43,61 @@ pub enum Error {
     /// A wrapper for `csv::Error`
     #[error(transparent)]
     Csv(#[from] csv::Error),
+    /// A quote appeared inside an unquoted field (strict mode only)
+    #[error("unescaped quote at byte {offset}")]
+    UnescapedQuote {
+        /// The byte offset of the quote within the line
+        offset: usize,
+    },
+    /// A quoted field was not closed before the end of the line (strict mode only)
+    #[error("unclosed quote at byte {offset}")]
+    UnclosedQuote {
+        /// The byte offset of the opening quote within the line
+        offset: usize,
+    },
+    /// Data followed a closing quote (strict mode only)
+    #[error("trailing data after quote at byte {offset}")]
+    TrailingDataAfterQuote {
+        /// The byte offset of the offending data within the line
+        offset: usize,
+    },
 }
 
 /// A type alias for `Result<T, csv_line::Error>`
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// The whitespace-trimming strategy applied to parsed fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Trim {
+    /// Do not trim any whitespace. This is the default.
+    #[default]
+    None,
+    /// Trim leading and trailing ASCII whitespace from fields.
+    ///
+    /// Only whitespace outside the quotes of a quoted field is stripped;
+    /// whitespace inside the quotes is kept.
+    Fields,
+    /// Trim leading and trailing ASCII whitespace from fields.
+    ///
+    /// Since a single line has no separate header concept, this behaves the
+    /// same as [`Trim::Fields`]; it mirrors the `csv` crate's naming.
+    All,
+}
+
+impl Trim {
+    fn enabled(self) -> bool {
+        matches!(self, Trim::Fields | Trim::All)
+    }
+}
+
 /// A struct to hold the parser settings
+#[derive(Clone)]
 pub struct CSVLine {
     separator: char,
+    quote: char,
+    escape: Option<char>,
+    trim: Trim,
+    strict: bool,
 }
 
 impl CSVLine {
@@ -56,22 +106,207 @@ impl CSVLine {
         Default::default()
     }
 
+    /// Guesses the field delimiter from a sample of lines.
+    ///
+    /// Each of the first few non-blank lines is split on every candidate
+    /// delimiter (`,`, `\t`, `;`, `|`, ` `) using the regular quoting-aware
+    /// parser, and the candidate whose per-line field count is the most
+    /// consistent — and greater than one — is returned. Ties are broken by the
+    /// higher field count, and `,` is returned when nothing beats a single
+    /// column.
+    pub fn sniff(sample: &str) -> char {
+        const CANDIDATES: [char; 5] = [',', '\t', ';', '|', ' '];
+        const MAX_LINES: usize = 10;
+
+        let lines: Vec<&str> = sample
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(MAX_LINES)
+            .collect();
+
+        let mut best = ',';
+        let mut best_score = (0usize, 0usize);
+        for &candidate in &CANDIDATES {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for line in &lines {
+                let fields =
+                    CsvRow::new(line.as_bytes(), candidate, '"', None, Trim::None, false).count();
+                *counts.entry(fields).or_default() += 1;
+            }
+            // The modal field count and how many lines agree on it. Ties on
+            // agreement are broken by the higher field count so the choice is
+            // deterministic regardless of the `HashMap`'s iteration order.
+            let Some((&modal_count, &agreement)) = counts
+                .iter()
+                .max_by_key(|(&count, &agreement)| (agreement, count))
+            else {
+                continue;
+            };
+            if modal_count <= 1 {
+                continue;
+            }
+            let score = (agreement, modal_count);
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        best
+    }
+
     /// Sets a new separator, the default is `,`
     pub fn with_separator(mut self, separator: char) -> Self {
         self.separator = separator;
         self
     }
 
+    /// Sets a new quoting character, the default is `"`
+    pub fn with_quote(mut self, quote: char) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Sets the escape character used inside quoted fields.
+    ///
+    /// By default (`None`) only the RFC-4180 doubled-quote escaping is
+    /// understood. When set to `Some(c)`, a `c` inside a quoted field makes
+    /// the following character literal (so `c` + quote yields a quote and
+    /// `c` + `c` yields `c`).
+    pub fn with_escape(mut self, escape: Option<char>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Sets the whitespace-trimming strategy, the default is [`Trim::None`]
+    pub fn with_trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Enables strict RFC-4180 parsing, the default is `false`
+    ///
+    /// In strict mode malformed input — an unescaped quote in an unquoted
+    /// field, an unclosed quoted field, or data after a closing quote — is
+    /// rejected with a typed [`Error`] instead of being recovered from.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
     /// Deserializes the string
     pub fn decode_str<T: DeserializeOwned>(&self, s: &str) -> Result<T> {
-        let record = StringRecord::from_iter(CsvRow::new(s, self.separator));
+        self.decode_bytes(s.as_bytes())
+    }
+
+    /// Deserializes a line from its raw bytes
+    ///
+    /// This is the lower-level entry point the string API is built on: the line
+    /// is scanned as raw bytes — jumping between structural bytes with `memchr`
+    /// in the common unquoted case — rather than going through a [`str`]. The
+    /// fields are still collected into a record before deserialization.
+    pub fn decode_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        let mut row = CsvRow::new(
+            bytes,
+            self.separator,
+            self.quote,
+            self.escape,
+            self.trim,
+            self.strict,
+        );
+        let mut record = ByteRecord::new();
+        for field in &mut row {
+            record.push_field(&field);
+        }
+        if let Some(error) = row.into_error() {
+            return Err(error);
+        }
         Ok(record.deserialize(None)?)
     }
+
+    /// Returns an iterator over the logical records of a multi-line input.
+    ///
+    /// Unlike [`decode_str`], which handles a single record, this splits `s`
+    /// into records while treating `\n`/`\r\n` inside a quoted field as
+    /// ordinary data, and deserializes each record into `T`.
+    ///
+    /// [`decode_str`]: CSVLine::decode_str
+    pub fn records<'a, T: DeserializeOwned>(&self, s: &'a str) -> Records<'a, T> {
+        Records {
+            config: self.clone(),
+            rest: s,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// An iterator over the deserialized logical records of a multi-line input.
+///
+/// Created by [`CSVLine::records`] or the [`from_records`] convenience
+/// function.
+pub struct Records<'a, T> {
+    config: CSVLine,
+    rest: &'a str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Iterator for Records<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        // Scan for the next record boundary, staying inside quoted fields.
+        let mut in_quotes = false;
+        let mut chars = self.rest.char_indices().peekable();
+        let mut boundary = None;
+        while let Some((i, ch)) = chars.next() {
+            if in_quotes {
+                if self.config.escape == Some(ch) {
+                    chars.next();
+                } else if ch == self.config.quote {
+                    if chars.peek().map(|&(_, c)| c) == Some(self.config.quote) {
+                        // A doubled quote is an escaped quote, not a closer.
+                        chars.next();
+                    } else {
+                        in_quotes = false;
+                    }
+                }
+            } else if ch == self.config.quote {
+                in_quotes = true;
+            } else if ch == '\n' {
+                boundary = Some((i, i + 1));
+                break;
+            } else if ch == '\r' {
+                let next = if chars.peek().map(|&(_, c)| c) == Some('\n') {
+                    chars.next().map(|(j, _)| j + 1).unwrap_or(i + 1)
+                } else {
+                    i + 1
+                };
+                boundary = Some((i, next));
+                break;
+            }
+        }
+
+        let (record, rest) = match boundary {
+            Some((end, next)) => (&self.rest[..end], &self.rest[next..]),
+            None => (self.rest, ""),
+        };
+        self.rest = rest;
+        Some(self.config.decode_str(record))
+    }
 }
 
 impl Default for CSVLine {
     fn default() -> Self {
-        Self { separator: ',' }
+        Self {
+            separator: ',',
+            quote: '"',
+            escape: None,
+            trim: Trim::None,
+            strict: false,
+        }
     }
 }
 
@@ -80,6 +315,18 @@ pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
     CSVLine::new().decode_str(s)
 }
 
+/// Deserializes a line from its raw bytes
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    CSVLine::new().decode_bytes(bytes)
+}
+
+/// Deserializes a multi-line input into an iterator of records
+///
+/// Newlines inside quoted fields are kept as data; see [`CSVLine::records`].
+pub fn from_records<T: DeserializeOwned>(s: &str) -> Records<'_, T> {
+    CSVLine::new().records(s)
+}
+
 /// Deserialize a csv formatted &str where the separator is specified
 ///
 /// # Arguments
@@ -149,6 +396,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn custom_quote() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, String);
+        assert_eq!(
+            CSVLine::new()
+                .with_quote('\'')
+                .decode_str::<Foo>("'foo,bar',baz")
+                .unwrap(),
+            Foo("foo,bar".into(), "baz".into())
+        );
+    }
+
+    #[test]
+    fn trim() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, String);
+        assert_eq!(
+            CSVLine::new()
+                .with_trim(Trim::All)
+                .decode_str::<Foo>(" foo , bar ")
+                .unwrap(),
+            Foo("foo".into(), "bar".into())
+        );
+    }
+
+    #[test]
+    fn strict() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, String);
+        assert_eq!(
+            CSVLine::new().strict(true).decode_str::<Foo>("foo,bar").unwrap(),
+            Foo("foo".into(), "bar".into())
+        );
+        assert!(matches!(
+            CSVLine::new().strict(true).decode_str::<Foo>(r#""foo" ,bar"#),
+            Err(Error::TrailingDataAfterQuote { offset: 5 })
+        ));
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(CSVLine::sniff("a,b,c\nd,e,f"), ',');
+        assert_eq!(CSVLine::sniff("a\tb\tc\nd\te\tf"), '\t');
+        assert_eq!(CSVLine::sniff("a;b;c\nd;e;f"), ';');
+        assert_eq!(CSVLine::sniff("a|b|c\nd|e|f"), '|');
+        // Quoted delimiters are not counted, so the consistent one wins.
+        assert_eq!(CSVLine::sniff("\"a;b\";c;d\nx;y;z"), ';');
+        // Nothing splits into more than one column -> default comma.
+        assert_eq!(CSVLine::sniff("single\ncolumn"), ',');
+    }
+
+    #[test]
+    fn records() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, String);
+        let input = "\"foo\nbar\",baz\nqux,quux";
+        let rows: Vec<Foo> = from_records::<Foo>(input)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Foo("foo\nbar".into(), "baz".into()),
+                Foo("qux".into(), "quux".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, i32);
+        assert_eq!(
+            from_bytes::<Foo>(b"foo,42").unwrap(),
+            Foo("foo".into(), 42)
+        );
+    }
+
+    #[test]
+    fn multibyte_separator() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Foo(String, String);
+        assert_eq!(
+            from_str_sep::<Foo>("foo€bar", '€').unwrap(),
+            Foo("foo".into(), "bar".into())
+        );
+        assert_eq!(
+            CSVLine::new()
+                .with_quote('“')
+                .decode_str::<Foo>("“foo,bar“,baz")
+                .unwrap(),
+            Foo("foo,bar".into(), "baz".into())
+        );
+    }
+
     #[test]
     fn tsv() {
         #[derive(Debug, PartialEq, Deserialize)]
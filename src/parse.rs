@@ -1,22 +1,66 @@
-use std::{borrow::Cow, str::CharIndices};
+use std::borrow::Cow;
+
+use crate::{Error, Trim};
+
+/// A structural character (delimiter, quote, or escape) held as its UTF-8
+/// bytes so the byte scanner can match multi-byte characters, not just ASCII.
+#[derive(Clone, Copy)]
+struct Token {
+    buf: [u8; 4],
+    len: usize,
+}
+
+impl Token {
+    fn new(c: char) -> Self {
+        let mut buf = [0u8; 4];
+        let len = c.encode_utf8(&mut buf).len();
+        Self { buf, len }
+    }
+
+    /// The UTF-8 encoding of the character.
+    fn bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// The leading byte, used to seed the `memchr` fast path.
+    fn first(&self) -> u8 {
+        self.buf[0]
+    }
+}
 
 #[derive(Debug)]
 enum ParseState {
     NewField,
     UnquotedField,
     QuotedField,
+    EscapeInQuotedField,
     QuoteInQuotedField,
     UnquotedDataAfterQuotedField(usize),
 }
 
 /// CSV row
+///
+/// Operates directly over the raw bytes of a line, borrowing field slices and
+/// only allocating when a field needs unescaping or has data after its closing
+/// quote. The common all-unquoted case
+/// jumps between structural bytes with `memchr` instead of visiting every byte.
 pub(crate) struct CsvRow<'a> {
     /// The line to parse
-    line: &'a str,
+    line: &'a [u8],
     /// The field delimiter
-    delimiter: char,
-    delimiter_len: usize,
-    char_indices: CharIndices<'a>,
+    delimiter: Token,
+    /// The quoting character
+    quote: Token,
+    /// The escape character, if any
+    escape: Option<Token>,
+    /// The whitespace-trimming strategy
+    trim: Trim,
+    /// Whether malformed input is rejected instead of recovered from
+    strict: bool,
+    /// The first error encountered while parsing in strict mode
+    error: Option<Error>,
+    /// The current byte offset
+    pos: usize,
     /// The starting position of the current column
     column_start: usize,
     /// Whether the iterator is finished
@@ -26,141 +70,304 @@ pub(crate) struct CsvRow<'a> {
 
 impl<'a> CsvRow<'a> {
     /// Create a new iterator
-    pub(crate) fn new(line: &'a str, delimiter: char) -> Self {
-        let char_indices = line.char_indices();
+    pub(crate) fn new(
+        line: &'a [u8],
+        delimiter: char,
+        quote: char,
+        escape: Option<char>,
+        trim: Trim,
+        strict: bool,
+    ) -> Self {
         Self {
             line,
-            delimiter,
-            delimiter_len: delimiter.len_utf8(),
-            char_indices,
+            delimiter: Token::new(delimiter),
+            quote: Token::new(quote),
+            escape: escape.map(Token::new),
+            trim,
+            strict,
+            error: None,
+            pos: 0,
             column_start: 0,
             done: false,
             column_needs_unescaping: false,
         }
     }
 
-    fn maybe_unescape(&self, start: usize, end: usize) -> Cow<'a, str> {
+    /// Consumes the iterator and returns the first strict-mode error, if any.
+    pub(crate) fn into_error(self) -> Option<Error> {
+        self.error
+    }
+
+    /// Records a strict-mode error, stops iteration and yields no column.
+    fn fail(&mut self, error: Error) -> Option<Cow<'a, [u8]>> {
+        self.error = Some(error);
+        self.done = true;
+        None
+    }
+
+    fn maybe_unescape(&self, start: usize, end: usize) -> Cow<'a, [u8]> {
         let content = &self.line[start..end];
-        if self.column_needs_unescaping {
-            Cow::Owned(content.replace("\"\"", "\""))
+        if !self.column_needs_unescaping {
+            return Cow::Borrowed(content);
+        }
+        let mut unescaped = Vec::with_capacity(content.len());
+        let mut i = 0;
+        if let Some(escape) = self.escape {
+            let escape = escape.bytes();
+            let quote = self.quote.bytes();
+            // Collapse `escape + <char>` into the literal `<char>`, and, as the
+            // `csv` crate does by default, still collapse a doubled quote.
+            while i < content.len() {
+                if content[i..].starts_with(escape) {
+                    i += escape.len();
+                    if i < content.len() {
+                        unescaped.push(content[i]);
+                        i += 1;
+                    }
+                } else if content[i..].starts_with(quote)
+                    && content[i + quote.len()..].starts_with(quote)
+                {
+                    unescaped.extend_from_slice(quote);
+                    i += 2 * quote.len();
+                } else {
+                    unescaped.push(content[i]);
+                    i += 1;
+                }
+            }
         } else {
-            Cow::Borrowed(content)
+            // Collapse a doubled quote into a single quote.
+            let quote = self.quote.bytes();
+            while i < content.len() {
+                if content[i..].starts_with(quote) && content[i + quote.len()..].starts_with(quote) {
+                    unescaped.extend_from_slice(quote);
+                    i += 2 * quote.len();
+                } else {
+                    unescaped.push(content[i]);
+                    i += 1;
+                }
+            }
         }
+        Cow::Owned(unescaped)
     }
 
-    fn format_partially_unquoted(&self, unquoted_start: usize, end: usize) -> Cow<'a, str> {
-        let quoted = self.maybe_unescape(self.column_start + 1, unquoted_start - 1);
-        let unquoted = &self.line[unquoted_start..end];
-        Cow::Owned(format!("{quoted}{unquoted}"))
+    fn format_partially_unquoted(&self, unquoted_start: usize, end: usize) -> Cow<'a, [u8]> {
+        let quoted =
+            self.maybe_unescape(self.column_start + self.quote.len, unquoted_start - self.quote.len);
+        let unquoted = self.trim_unquoted(&self.line[unquoted_start..end]);
+        if unquoted.is_empty() {
+            // Only trailing whitespace followed the closing quote.
+            return quoted;
+        }
+        let mut column = quoted.into_owned();
+        column.extend_from_slice(unquoted);
+        Cow::Owned(column)
+    }
+
+    /// Trims leading and trailing ASCII whitespace when trimming is enabled.
+    fn trim_unquoted(&self, s: &'a [u8]) -> &'a [u8] {
+        if !self.trim.enabled() {
+            return s;
+        }
+        let start = s
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(s.len());
+        let end = s
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(start, |i| i + 1);
+        &s[start..end]
     }
 }
 
 /// An iterator over the columns of a CSV row
 impl<'a> Iterator for CsvRow<'a> {
-    type Item = Cow<'a, str>;
+    type Item = Cow<'a, [u8]>;
 
     /// Returns the next column in the row
     fn next(&mut self) -> Option<Self::Item> {
         let mut state = ParseState::NewField;
         self.column_needs_unescaping = false;
 
-        // Loop over the characters in the line
+        // Loop over the bytes in the line
         loop {
             if self.done {
                 return None;
             }
 
-            let Some((ch_pos, ch)) = self.char_indices.next() else {
+            // Fast path: in an unquoted field, jump straight to the next
+            // structural byte rather than visiting each one.
+            if !self.strict {
+                if let ParseState::UnquotedField = state {
+                    match memchr::memchr3(self.delimiter.first(), b'\n', b'\r', &self.line[self.pos..])
+                    {
+                        Some(rel) => self.pos += rel,
+                        None => {
+                            let column = self.trim_unquoted(&self.line[self.column_start..]);
+                            self.done = true;
+                            return Some(Cow::Borrowed(column));
+                        }
+                    }
+                }
+            }
+
+            let Some(&byte) = self.line.get(self.pos) else {
                 // The end of the line has been reached
                 self.done = true;
                 return match state {
                     ParseState::NewField => {
                         // The line ended at the end of the previous column.
                         // If the previous column ended with a delimiter, add an empty column.
-                        (self.line.chars().last() == Some(self.delimiter)).then(|| "".into())
+                        self.line
+                            .ends_with(self.delimiter.bytes())
+                            .then(|| Cow::Borrowed(&[][..]))
                     }
                     ParseState::UnquotedField => {
                         // The line ended in an unquoted field
-                        Some(self.line[self.column_start..].into())
+                        Some(Cow::Borrowed(self.trim_unquoted(&self.line[self.column_start..])))
                     }
-                    ParseState::QuotedField => {
+                    ParseState::QuotedField | ParseState::EscapeInQuotedField => {
                         // The line ended in an unclosed quoted field
-                        Some(self.maybe_unescape(self.column_start + 1, self.line.len()))
+                        if self.strict {
+                            self.error = Some(Error::UnclosedQuote {
+                                offset: self.column_start,
+                            });
+                            None
+                        } else {
+                            Some(self.maybe_unescape(self.column_start + self.quote.len, self.line.len()))
+                        }
                     }
                     ParseState::QuoteInQuotedField => {
                         // The line ended in a properly closed quoted field
-                        Some(self.maybe_unescape(self.column_start + 1, self.line.len() - 1))
+                        Some(self.maybe_unescape(
+                            self.column_start + self.quote.len,
+                            self.line.len() - self.quote.len,
+                        ))
                     }
                     ParseState::UnquotedDataAfterQuotedField(unquoted_start) => {
-                        let column =
-                            self.format_partially_unquoted(unquoted_start, self.line.len());
-                        Some(column.into())
+                        Some(self.format_partially_unquoted(unquoted_start, self.line.len()))
                     }
                 };
             };
 
+            let pos = self.pos;
+            // Structural characters can be multi-byte, so match their whole
+            // UTF-8 encoding at the current position rather than a single byte.
+            let rest = &self.line[pos..];
+            let is_delimiter = rest.starts_with(self.delimiter.bytes());
+            let is_quote = rest.starts_with(self.quote.bytes());
+            let is_escape = match self.escape {
+                Some(escape) => rest.starts_with(escape.bytes()),
+                None => false,
+            };
+            let delimiter_len = self.delimiter.len;
+            let quote_len = self.quote.len;
+
             match state {
                 ParseState::NewField => {
-                    if ch == self.delimiter {
+                    if is_delimiter {
                         // An empty column was found
-                        self.column_start = ch_pos + self.delimiter_len;
-                        return Some("".into());
+                        self.column_start = pos + delimiter_len;
+                        self.pos = pos + delimiter_len;
+                        return Some(Cow::Borrowed(&[][..]));
+                    }
+                    if self.trim.enabled()
+                        && byte != b'\n'
+                        && byte != b'\r'
+                        && byte.is_ascii_whitespace()
+                    {
+                        // Skip leading whitespace before the field starts.
+                        self.column_start = pos + 1;
+                        self.pos = pos + 1;
+                        continue;
                     }
-                    if ch == '"' {
+                    if is_quote {
                         state = ParseState::QuotedField;
+                        self.pos = pos + quote_len;
                     } else {
                         state = ParseState::UnquotedField;
+                        self.pos = pos + 1;
                     }
                 }
                 ParseState::UnquotedField => {
-                    if ch == self.delimiter {
-                        let column = &self.line[self.column_start..ch_pos];
-                        self.column_start = ch_pos + self.delimiter_len;
-                        return Some(column.into());
+                    if self.strict && is_quote {
+                        return self.fail(Error::UnescapedQuote { offset: pos });
+                    }
+                    if is_delimiter {
+                        let column = self.trim_unquoted(&self.line[self.column_start..pos]);
+                        self.column_start = pos + delimiter_len;
+                        self.pos = pos + delimiter_len;
+                        return Some(Cow::Borrowed(column));
                     }
 
-                    if ch == '\n' || ch == '\r' {
-                        let column = &self.line[self.column_start..ch_pos];
+                    if byte == b'\n' || byte == b'\r' {
+                        let column = self.trim_unquoted(&self.line[self.column_start..pos]);
                         self.done = true;
-                        return Some(column.into());
+                        return Some(Cow::Borrowed(column));
                     }
+                    self.pos = pos + 1;
                 }
                 ParseState::QuotedField => {
-                    if ch == '"' {
+                    if is_escape {
+                        // An escape character was found, so the next character
+                        // is taken verbatim and the field will need unescaping.
+                        self.column_needs_unescaping = true;
+                        state = ParseState::EscapeInQuotedField;
+                        self.pos = pos + self.escape.map_or(1, |e| e.len);
+                    } else if is_quote {
                         state = ParseState::QuoteInQuotedField;
+                        self.pos = pos + quote_len;
+                    } else {
+                        self.pos = pos + 1;
                     }
                 }
+                ParseState::EscapeInQuotedField => {
+                    // The escaped byte is part of the field, continue quoting.
+                    state = ParseState::QuotedField;
+                    self.pos = pos + 1;
+                }
                 ParseState::QuoteInQuotedField => {
-                    if ch == '"' {
+                    if is_quote {
                         // An escaped quote was found, so continue in the quoted field.
                         self.column_needs_unescaping = true;
                         state = ParseState::QuotedField;
+                        self.pos = pos + quote_len;
                         continue;
                     }
 
-                    if ch == self.delimiter {
+                    if is_delimiter {
                         // The end of the quoted field has been reached
-                        let column = self.maybe_unescape(self.column_start + 1, ch_pos - 1);
-                        self.column_start = ch_pos + self.delimiter_len;
+                        let column =
+                            self.maybe_unescape(self.column_start + quote_len, pos - quote_len);
+                        self.column_start = pos + delimiter_len;
+                        self.pos = pos + delimiter_len;
                         return Some(column);
                     }
 
-                    if ch == '\n' || ch == '\r' {
+                    if byte == b'\n' || byte == b'\r' {
                         // The end of the line has been reached after a quoted field.
-                        let column = self.maybe_unescape(self.column_start + 1, ch_pos - 1);
+                        let column =
+                            self.maybe_unescape(self.column_start + quote_len, pos - quote_len);
                         self.done = true;
                         return Some(column);
                     }
 
+                    if self.strict {
+                        return self.fail(Error::TrailingDataAfterQuote { offset: pos });
+                    }
+
                     // Data was found after a quoted field, so treat it as an unquoted continuation.
-                    state = ParseState::UnquotedDataAfterQuotedField(ch_pos);
+                    state = ParseState::UnquotedDataAfterQuotedField(pos);
+                    self.pos = pos + 1;
                 }
                 ParseState::UnquotedDataAfterQuotedField(unquoted_start) => {
-                    if ch == self.delimiter {
-                        let column = self.format_partially_unquoted(unquoted_start, ch_pos);
-                        self.column_start = ch_pos + self.delimiter_len;
-                        return Some(column.into());
+                    if is_delimiter {
+                        let column = self.format_partially_unquoted(unquoted_start, pos);
+                        self.column_start = pos + delimiter_len;
+                        self.pos = pos + delimiter_len;
+                        return Some(column);
                     }
+                    self.pos = pos + 1;
                 }
             }
         }
@@ -181,14 +388,36 @@ mod tests {
         row.unwrap().iter().map(ToOwned::to_owned).collect()
     }
 
+    fn collect(row: CsvRow<'_>) -> Vec<String> {
+        row.map(|c| String::from_utf8(c.into_owned()).unwrap())
+            .collect()
+    }
+
     fn parse_line(line: &str) -> Vec<String> {
-        let row = CsvRow::new(line, ',');
-        row.into_iter().map(Cow::into_owned).collect()
+        collect(CsvRow::new(line.as_bytes(), ',', '"', None, Trim::None, false))
     }
 
     fn parse_line_with_delimiter(line: &str, delimiter: char) -> Vec<String> {
-        let row = CsvRow::new(line, delimiter);
-        row.into_iter().map(Cow::into_owned).collect()
+        collect(CsvRow::new(line.as_bytes(), delimiter, '"', None, Trim::None, false))
+    }
+
+    fn parse_line_with_escape(line: &str, escape: char) -> Vec<String> {
+        collect(CsvRow::new(line.as_bytes(), ',', '"', Some(escape), Trim::None, false))
+    }
+
+    fn parse_line_with_trim(line: &str) -> Vec<String> {
+        collect(CsvRow::new(line.as_bytes(), ',', '"', None, Trim::All, false))
+    }
+
+    fn parse_line_strict(line: &str) -> std::result::Result<Vec<String>, Error> {
+        let mut row = CsvRow::new(line.as_bytes(), ',', '"', None, Trim::None, true);
+        let columns = (&mut row)
+            .map(|c| String::from_utf8(c.into_owned()).unwrap())
+            .collect();
+        match row.into_error() {
+            Some(error) => Err(error),
+            None => Ok(columns),
+        }
     }
 
     /// Tests the line and ensures the result matches the output of `rust_csv`.
@@ -365,4 +594,71 @@ mod tests {
             ["f;oo", "", "bar"]
         );
     }
+
+    // =========================================================================
+    // BACKSLASH-STYLE ESCAPING
+    // =========================================================================
+
+    /// Not covered by RFC 4180, but supported by the older `csv` crate's
+    /// reader: an escape character makes the following character literal.
+    #[test]
+    fn backslash_escape() {
+        assert_eq!(
+            parse_line_with_escape(r#""foo\"bar""#, '\\'),
+            [r#"foo"bar"#]
+        );
+        assert_eq!(
+            parse_line_with_escape(r#""foo\\bar""#, '\\'),
+            [r#"foo\bar"#]
+        );
+        assert_eq!(parse_line_with_escape(r#""a\"b",c"#, '\\'), [r#"a"b"#, "c"]);
+    }
+
+    /// With an escape char configured, RFC doubled-quotes are still collapsed,
+    /// matching the `csv` crate's default `double_quote` behavior.
+    #[test]
+    fn escape_with_doubled_quote() {
+        assert_eq!(parse_line_with_escape(r#""a""b""#, '\\'), [r#"a"b"#]);
+    }
+
+    // =========================================================================
+    // WHITESPACE TRIMMING (opt-in)
+    // =========================================================================
+
+    /// With trimming enabled, whitespace outside the field is stripped, while
+    /// whitespace inside quotes is preserved.
+    #[test]
+    fn trim_fields() {
+        assert_eq!(parse_line_with_trim(" foo , bar "), ["foo", "bar"]);
+        assert_eq!(parse_line_with_trim(r#""foo" , "bar""#), ["foo", "bar"]);
+        assert_eq!(parse_line_with_trim(r#"" foo "," bar ""#), [" foo ", " bar "]);
+        assert_eq!(parse_line_with_trim("foo, ,bar"), ["foo", "", "bar"]);
+    }
+
+    // =========================================================================
+    // STRICT MODE
+    // =========================================================================
+
+    /// In strict mode the lossy recovery is replaced by typed errors.
+    #[test]
+    fn strict_mode() {
+        assert_eq!(parse_line_strict("foo,bar").unwrap(), ["foo", "bar"]);
+        assert_eq!(
+            parse_line_strict(r#""foo,bar",baz"#).unwrap(),
+            ["foo,bar", "baz"]
+        );
+
+        assert!(matches!(
+            parse_line_strict(r#"foo"bar"#),
+            Err(Error::UnescapedQuote { offset: 3 })
+        ));
+        assert!(matches!(
+            parse_line_strict(r#""foo"#),
+            Err(Error::UnclosedQuote { offset: 0 })
+        ));
+        assert!(matches!(
+            parse_line_strict(r#""foo" ,bar"#),
+            Err(Error::TrailingDataAfterQuote { offset: 5 })
+        ));
+    }
 }